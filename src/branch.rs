@@ -4,7 +4,9 @@ use std::marker;
 use std::ptr;
 use std::str;
 
-use {raw, Error, Reference, BranchType, References};
+use libc::c_int;
+
+use {raw, Buf, Commit, Error, ErrorCode, Reference, ReferenceFormat, Time, BranchType, References};
 use util::Binding;
 
 /// A structure to represent a git [branch][1]
@@ -44,6 +46,22 @@ impl<'repo> Branch<'repo> {
         unsafe { raw::git_branch_is_head(&*self.get().raw()) == 1 }
     }
 
+    /// Get the tip commit of this branch.
+    ///
+    /// This is shorthand for peeling the branch's underlying reference down
+    /// to the commit it points at.
+    pub fn get_commit(&self) -> Result<Commit<'repo>, Error> {
+        self.get().peel_to_commit()
+    }
+
+    /// Get the committer time of this branch's tip commit.
+    ///
+    /// Useful for sorting a branch list by recency of activity; see also
+    /// `get_commit`.
+    pub fn last_commit_time(&self) -> Result<Time, Error> {
+        Ok(try!(self.get_commit()).time())
+    }
+
     /// Move/rename an existing local branch reference.
     pub fn rename(&mut self, new_branch_name: &str, force: bool)
                   -> Result<Branch<'repo>, Error> {
@@ -56,6 +74,20 @@ impl<'repo> Branch<'repo> {
         }
     }
 
+    /// Determine whether a branch name is well-formed, e.g. before passing
+    /// it to `Repository::branch` or `rename`.
+    ///
+    /// This only checks that the name is syntactically legal; it does not
+    /// check whether a branch by that name already exists.
+    pub fn name_is_valid(name: &str) -> Result<bool, Error> {
+        let name = try!(CString::new(name));
+        let mut valid: c_int = 0;
+        unsafe {
+            try_call!(raw::git_branch_name_is_valid(&mut valid, name));
+        }
+        Ok(valid == 1)
+    }
+
     /// Return the name of the given local or remote branch.
     ///
     /// May return `Ok(None)` if the name is not valid utf-8.
@@ -82,6 +114,109 @@ impl<'repo> Branch<'repo> {
         }
     }
 
+    /// Lookup the name of the reference that would support this branch's
+    /// remote-tracking branch, without requiring that reference to actually
+    /// exist.
+    ///
+    /// Unlike `upstream`, this reads the answer straight out of the
+    /// `branch.<name>.remote`/`branch.<name>.merge` configuration, so it
+    /// succeeds even if the remote-tracking ref has never been fetched into
+    /// this repository.
+    ///
+    /// May return `Ok(None)` if no upstream is configured for this branch.
+    pub fn upstream_name_bytes(&self) -> Result<Option<Vec<u8>>, Error> {
+        optional_buf(self.upstream_name_buf())
+    }
+
+    /// Like `upstream_name_bytes` but returns a `String` instead.
+    ///
+    /// May return `Ok(None)` if no upstream is configured for this branch,
+    /// or if its name is not valid utf-8.
+    pub fn upstream_name(&self) -> Result<Option<String>, Error> {
+        Ok(try!(self.upstream_name_bytes()).and_then(|bytes| String::from_utf8(bytes).ok()))
+    }
+
+    fn upstream_name_buf(&self) -> Result<Buf, Error> {
+        let name = try!(CString::new(self.get().name_bytes()));
+        let buf = Buf::new();
+        unsafe {
+            try_call!(raw::git_branch_upstream_name(buf.raw(),
+                                                     raw::git_reference_owner(self.get().raw()),
+                                                     name));
+        }
+        Ok(buf)
+    }
+
+    /// Return the name of the remote that a remote-tracking branch belongs
+    /// to, e.g. `origin` for `refs/remotes/origin/master`.
+    ///
+    /// May return `Ok(None)` if this branch has no associated remote (e.g.
+    /// it is a local branch with no remote-tracking ref).
+    pub fn remote_name(&self) -> Result<Option<Vec<u8>>, Error> {
+        if !self.get().is_remote() {
+            return Ok(None);
+        }
+        optional_buf(self.remote_name_buf())
+    }
+
+    fn remote_name_buf(&self) -> Result<Buf, Error> {
+        let name = try!(CString::new(self.get().name_bytes()));
+        let buf = Buf::new();
+        unsafe {
+            try_call!(raw::git_branch_remote_name(buf.raw(),
+                                                   raw::git_reference_owner(self.get().raw()),
+                                                   name));
+        }
+        Ok(buf)
+    }
+
+    /// Return the name of the remote configured as this local branch's
+    /// upstream (`branch.<name>.remote`), without resolving the
+    /// remote-tracking reference itself.
+    ///
+    /// Requires a libgit2 new enough to export `git_branch_upstream_remote`
+    /// (0.24+); if the pinned libgit2-sys predates that, this will fail to
+    /// link.
+    ///
+    /// May return `Ok(None)` if no upstream remote is configured.
+    pub fn upstream_remote_name(&self) -> Result<Option<Vec<u8>>, Error> {
+        optional_buf(self.upstream_remote_buf())
+    }
+
+    fn upstream_remote_buf(&self) -> Result<Buf, Error> {
+        let name = try!(CString::new(self.get().name_bytes()));
+        let buf = Buf::new();
+        unsafe {
+            try_call!(raw::git_branch_upstream_remote(buf.raw(),
+                                                       raw::git_reference_owner(self.get().raw()),
+                                                       name));
+        }
+        Ok(buf)
+    }
+
+    /// Return the configured upstream merge ref for this local branch
+    /// (`branch.<name>.merge`), e.g. `refs/heads/master`.
+    ///
+    /// Requires a libgit2 new enough to export `git_branch_upstream_merge`
+    /// (0.24+); if the pinned libgit2-sys predates that, this will fail to
+    /// link.
+    ///
+    /// May return `Ok(None)` if no upstream merge ref is configured.
+    pub fn upstream_merge(&self) -> Result<Option<Vec<u8>>, Error> {
+        optional_buf(self.upstream_merge_buf())
+    }
+
+    fn upstream_merge_buf(&self) -> Result<Buf, Error> {
+        let name = try!(CString::new(self.get().name_bytes()));
+        let buf = Buf::new();
+        unsafe {
+            try_call!(raw::git_branch_upstream_merge(buf.raw(),
+                                                      raw::git_reference_owner(self.get().raw()),
+                                                      name));
+        }
+        Ok(buf)
+    }
+
     /// Set the upstream configuration for a given local branch.
     ///
     /// If `None` is specified, then the upstream branch is unset. The name
@@ -97,6 +232,29 @@ impl<'repo> Branch<'repo> {
     }
 }
 
+/// Validate and normalize a proposed branch name, the same way
+/// `Reference::normalize_name` does for a full reference name, applied to the
+/// name as it would appear under `refs/heads/` (so `foo/bar` is accepted).
+///
+/// Returns the normalized name on success, or an error if the name is not a
+/// valid branch name.
+pub fn normalize_branch_name(name: &str) -> Result<String, Error> {
+    const PREFIX: &'static str = "refs/heads/";
+    let refname = format!("{}{}", PREFIX, name);
+    let normalized = try!(Reference::normalize_name(&refname, ReferenceFormat::ALLOW_ONELEVEL));
+    Ok(normalized[PREFIX.len()..].to_string())
+}
+
+/// Turn a "not found" error from a config-backed `Buf` lookup into `None`,
+/// leaving other errors untouched.
+fn optional_buf(result: Result<Buf, Error>) -> Result<Option<Vec<u8>>, Error> {
+    match result {
+        Ok(buf) => Ok(Some(buf.to_vec())),
+        Err(ref e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 impl<'repo> Branches<'repo> {
     /// Creates a new iterator from the raw pointer given.
     ///
@@ -163,6 +321,48 @@ impl<'repo> PartialOrd for Branch<'repo> {
     }
 }
 
+/// A newtype wrapper around `Branch` that orders by the tip commit's
+/// committer time, descending, instead of by name.
+///
+/// This is useful for "most recently active branch first" views, where the
+/// default name-based `Ord` on `Branch` is the wrong order. Branches with
+/// equal commit times are ordered by name, the same way `Branch`'s own `Ord`
+/// does. A branch whose tip commit can't be resolved sorts after every branch
+/// whose tip commit can; two such branches are ordered by name.
+pub struct ByRecency<'repo>(pub Branch<'repo>);
+
+impl<'repo> ByRecency<'repo> {
+    fn commit_seconds(&self) -> Option<i64> {
+        self.0.last_commit_time().ok().map(|t| t.seconds())
+    }
+}
+
+impl<'repo> Eq for ByRecency<'repo> {}
+
+impl<'repo> Ord for ByRecency<'repo> {
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        match (self.commit_seconds(), rhs.commit_seconds()) {
+            (Some(lhs), Some(rhs_secs)) =>
+                rhs_secs.cmp(&lhs).then_with(|| self.0.cmp(&rhs.0)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => self.0.cmp(&rhs.0),
+        }
+    }
+}
+
+impl<'repo> PartialEq for ByRecency<'repo> {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.cmp(rhs) == Ordering::Equal
+    }
+}
+
+impl<'repo> PartialOrd for ByRecency<'repo> {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use BranchType;
@@ -176,6 +376,8 @@ mod tests {
 
         let mut b1 = repo.branch("foo", &commit, false).unwrap();
         assert!(!b1.is_head());
+        assert_eq!(b1.get_commit().unwrap().id(), commit.id());
+        assert_eq!(b1.last_commit_time().unwrap().seconds(), commit.time().seconds());
         repo.branch("foo2", &commit, false).unwrap();
 
         assert_eq!(repo.branches(None).unwrap().count(), 3);
@@ -183,13 +385,31 @@ mod tests {
         let mut b1 = b1.rename("bar", false).unwrap();
         assert_eq!(b1.name().unwrap(), Some("bar"));
         assert!(b1.upstream().is_err());
+        assert_eq!(b1.upstream_name().unwrap(), None);
+        assert_eq!(b1.remote_name().unwrap(), None);
         b1.set_upstream(Some("master")).unwrap();
         b1.upstream().unwrap();
+        assert_eq!(b1.upstream_name().unwrap(), Some("refs/heads/master".to_string()));
         b1.set_upstream(None).unwrap();
+        assert_eq!(b1.upstream_name().unwrap(), None);
 
         b1.delete().unwrap();
     }
 
+    #[test]
+    fn name_validation() {
+        use Branch;
+        use super::normalize_branch_name;
+
+        assert!(Branch::name_is_valid("foo").unwrap());
+        assert!(Branch::name_is_valid("foo/bar").unwrap());
+        assert!(!Branch::name_is_valid("foo..bar").unwrap());
+        assert!(!Branch::name_is_valid("").unwrap());
+
+        assert_eq!(normalize_branch_name("foo/bar").unwrap(), "foo/bar");
+        assert!(normalize_branch_name("foo..bar").is_err());
+    }
+
     #[test]
     fn cmp() {
         use std::cmp::Ordering;
@@ -224,4 +444,57 @@ mod tests {
         assert!(foo != moo);
         assert!(moo != foo);
     }
+
+    #[test]
+    fn by_recency() {
+        use std::cmp::Ordering;
+        use {Signature, Time};
+        use super::ByRecency;
+
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap();
+        let target = head.target().unwrap();
+        let commit = repo.find_commit(target).unwrap();
+
+        // Commit an explicit 1000 seconds after the fixture's tip, so the
+        // newer branch's recency is unambiguous regardless of wall-clock
+        // time, and name it so it would sort *after* the older branch
+        // alphabetically -- this is what actually exercises the time-based
+        // comparison instead of happening to agree with name order.
+        let old_time = commit.time();
+        let new_time = Time::new(old_time.seconds() + 1000, old_time.offset());
+        let new_sig = Signature::new("new", "new@example.com", &new_time).unwrap();
+        let newer = repo.commit(
+            None,
+            &new_sig,
+            &new_sig,
+            "newer",
+            &commit.tree().unwrap(),
+            &[&commit]
+        ).unwrap();
+        let newer = repo.find_commit(newer).unwrap();
+
+        let aaa = ByRecency(repo.branch("aaa", &commit, false).unwrap());
+        let zzz = ByRecency(repo.branch("zzz", &newer, false).unwrap());
+
+        // "zzz" has the more recent commit, so it sorts first despite
+        // sorting last by name.
+        assert_eq!(zzz.cmp(&aaa), Ordering::Less);
+        assert_eq!(aaa.cmp(&zzz), Ordering::Greater);
+        assert_eq!(aaa.cmp(&aaa), Ordering::Equal);
+
+        // When commit times are equal, falls back to name order.
+        let tied_newer = repo.commit(
+            None,
+            &new_sig,
+            &new_sig,
+            "tied",
+            &commit.tree().unwrap(),
+            &[&commit]
+        ).unwrap();
+        let tied_newer = repo.find_commit(tied_newer).unwrap();
+        let aab = ByRecency(repo.branch("aab", &tied_newer, false).unwrap());
+        assert_eq!(zzz.cmp(&aab), Ordering::Greater);
+        assert_eq!(aab.cmp(&zzz), Ordering::Less);
+    }
 }